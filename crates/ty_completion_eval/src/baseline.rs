@@ -0,0 +1,282 @@
+//! A persisted baseline of per-test ranks and corpus MRR, used to ratchet
+//! completion quality over time.
+//!
+//! This borrows the save-metrics / ratchet-metrics design from rustc's
+//! compiletest: a prior run's results are checked into the repository as
+//! `baseline.json`, and subsequent runs compare against it so a regression
+//! in completion quality fails the run instead of silently slipping by.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::score::CorpusReport;
+
+/// The fractional drop in MRR (e.g. `0.005` for 0.5%) below which a change
+/// is considered noise rather than a regression.
+pub const DEFAULT_MRR_NOISE_THRESHOLD: f64 = 0.005;
+
+/// A baseline snapshot of a prior evaluation run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    mrr: f64,
+    /// Keyed on each score's name (`TestSource::name`, plus `::<case>` for a
+    /// named cursor case), so a renamed or newly added test is reported as
+    /// new rather than silently comparing against nothing.
+    tests: BTreeMap<String, BaselineEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineEntry {
+    rank: Option<usize>,
+}
+
+impl Baseline {
+    /// Build a baseline from the results of a completed evaluation run.
+    pub fn from_report(report: &CorpusReport) -> Baseline {
+        let tests = report
+            .scores
+            .iter()
+            .map(|score| (score.name.clone(), BaselineEntry { rank: score.rank }))
+            .collect();
+        Baseline {
+            mrr: report.mrr(),
+            tests,
+        }
+    }
+
+    /// Write this baseline as JSON to `path`, creating or overwriting it.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let data =
+            serde_json::to_string_pretty(self).context("failed to serialize baseline to JSON")?;
+        std::fs::write(path, data)
+            .with_context(|| format!("failed to write baseline to `{}`", path.display()))
+    }
+
+    /// Read a previously saved baseline from the JSON file at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Baseline> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("failed to read baseline from `{}`", path.display()))?;
+        serde_json::from_slice(&data)
+            .with_context(|| format!("failed to parse baseline JSON from `{}`", path.display()))
+    }
+
+    /// Compare this baseline against a fresh report, treating an MRR drop
+    /// smaller than `noise_threshold` as noise rather than a regression.
+    pub fn compare(&self, report: &CorpusReport, noise_threshold: f64) -> Comparison {
+        let tests = report
+            .scores
+            .iter()
+            .map(|score| TestComparison {
+                name: score.name.clone(),
+                change: match self.tests.get(&score.name) {
+                    None => RankChange::New { new: score.rank },
+                    Some(entry) => RankChange::of(entry.rank, score.rank),
+                },
+            })
+            .collect();
+        let mrr_delta = report.mrr() - self.mrr;
+        Comparison {
+            tests,
+            mrr_delta,
+            mrr_regressed: mrr_delta < -noise_threshold,
+        }
+    }
+}
+
+/// The outcome of comparing a fresh evaluation run against a baseline.
+#[derive(Debug)]
+pub struct Comparison {
+    tests: Vec<TestComparison>,
+    mrr_delta: f64,
+    mrr_regressed: bool,
+}
+
+impl Comparison {
+    /// Whether any test's rank regressed, or the corpus MRR fell beyond the
+    /// noise threshold.
+    pub fn has_regression(&self) -> bool {
+        self.mrr_regressed || self.tests.iter().any(|t| t.change.is_regression())
+    }
+
+    /// Print a per-test regression/improvement report plus the MRR delta.
+    pub fn print_summary(&self) {
+        println!();
+        println!("baseline comparison:");
+        for test in &self.tests {
+            if let Some(line) = test.change.summary_line(&test.name) {
+                println!("  {line}");
+            }
+        }
+        println!(
+            "  MRR: {delta:+.4} ({status})",
+            delta = self.mrr_delta,
+            status = if self.mrr_regressed {
+                "REGRESSED"
+            } else {
+                "ok"
+            },
+        );
+    }
+}
+
+#[derive(Debug)]
+struct TestComparison {
+    name: String,
+    change: RankChange,
+}
+
+/// How a single test's rank moved relative to the baseline.
+#[derive(Debug)]
+enum RankChange {
+    /// The test does not appear in the baseline at all.
+    New { new: Option<usize> },
+    /// The rank is unchanged (including "not found" both times).
+    Same,
+    /// The rank moved to a better (lower, or newly found) position.
+    Improved {
+        old: Option<usize>,
+        new: Option<usize>,
+    },
+    /// The rank moved to a worse (higher, or newly not-found) position.
+    Regressed {
+        old: Option<usize>,
+        new: Option<usize>,
+    },
+}
+
+impl RankChange {
+    fn of(old: Option<usize>, new: Option<usize>) -> RankChange {
+        match (old, new) {
+            (None, None) => RankChange::Same,
+            (None, Some(_)) => RankChange::Improved { old, new },
+            (Some(_), None) => RankChange::Regressed { old, new },
+            (Some(o), Some(n)) if o == n => RankChange::Same,
+            (Some(o), Some(n)) if n < o => RankChange::Improved { old, new },
+            (Some(_), Some(_)) => RankChange::Regressed { old, new },
+        }
+    }
+
+    fn is_regression(&self) -> bool {
+        matches!(self, RankChange::Regressed { .. })
+    }
+
+    fn summary_line(&self, name: &str) -> Option<String> {
+        match self {
+            RankChange::Same => None,
+            RankChange::New { new } => Some(format!("{name}: new test (rank={new:?})")),
+            RankChange::Improved { old, new } => {
+                Some(format!("{name}: improved ({old:?} -> {new:?})"))
+            }
+            RankChange::Regressed { old, new } => {
+                Some(format!("{name}: REGRESSED ({old:?} -> {new:?})"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::score::Score;
+
+    #[test]
+    fn rank_change_same_when_unchanged() {
+        assert!(matches!(RankChange::of(Some(3), Some(3)), RankChange::Same));
+        assert!(matches!(RankChange::of(None, None), RankChange::Same));
+    }
+
+    #[test]
+    fn rank_change_improved_when_rank_drops_or_is_newly_found() {
+        assert!(matches!(
+            RankChange::of(Some(5), Some(2)),
+            RankChange::Improved { .. }
+        ));
+        assert!(matches!(
+            RankChange::of(None, Some(1)),
+            RankChange::Improved { .. }
+        ));
+    }
+
+    #[test]
+    fn rank_change_regressed_when_rank_rises_or_is_newly_not_found() {
+        assert!(matches!(
+            RankChange::of(Some(2), Some(5)),
+            RankChange::Regressed { .. }
+        ));
+        assert!(matches!(
+            RankChange::of(Some(1), None),
+            RankChange::Regressed { .. }
+        ));
+        assert!(RankChange::of(Some(2), Some(5)).is_regression());
+        assert!(!RankChange::of(Some(5), Some(2)).is_regression());
+    }
+
+    fn report(scores: &[(&str, Option<usize>)]) -> CorpusReport {
+        CorpusReport {
+            scores: scores
+                .iter()
+                .map(|&(name, rank)| Score::new(name.to_string(), rank))
+                .collect(),
+        }
+    }
+
+    fn baseline(tests: &[(&str, Option<usize>)]) -> Baseline {
+        Baseline {
+            mrr: report(tests).mrr(),
+            tests: tests
+                .iter()
+                .map(|&(name, rank)| (name.to_string(), BaselineEntry { rank }))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn compare_flags_new_tests_without_regressing() {
+        let baseline = baseline(&[]);
+        let comparison = baseline.compare(&report(&[("a", Some(1))]), DEFAULT_MRR_NOISE_THRESHOLD);
+        assert!(!comparison.has_regression());
+        assert!(matches!(
+            comparison.tests[0].change,
+            RankChange::New { new: Some(1) }
+        ));
+    }
+
+    #[test]
+    fn compare_flags_regression() {
+        let baseline = baseline(&[("a", Some(1))]);
+        let comparison = baseline.compare(&report(&[("a", Some(5))]), DEFAULT_MRR_NOISE_THRESHOLD);
+        assert!(comparison.has_regression());
+    }
+
+    #[test]
+    fn compare_mrr_delta_under_threshold_is_not_a_regression() {
+        // MRR drops from 1.0 to 0.5, a delta of -0.5.
+        let baseline = baseline(&[("a", Some(1))]);
+        let report = report(&[("a", Some(2))]);
+        // -0.5 is not less than -0.6, so this is noise, not a regression.
+        assert!(!baseline.compare(&report, 0.6).mrr_regressed);
+    }
+
+    #[test]
+    fn compare_mrr_delta_beyond_threshold_is_a_regression() {
+        // MRR drops from 1.0 to 0.5, a delta of -0.5.
+        let baseline = baseline(&[("a", Some(1))]);
+        let report = report(&[("a", Some(2))]);
+        // -0.5 is less than -0.4, so this exceeds the noise threshold.
+        let comparison = baseline.compare(&report, 0.4);
+        assert!(comparison.mrr_regressed);
+        assert!(comparison.has_regression());
+    }
+
+    #[test]
+    fn compare_mrr_delta_exactly_at_threshold_is_not_a_regression() {
+        // The check is a strict `<`, so a delta exactly equal to (negative)
+        // the threshold does not count as a regression.
+        let baseline = baseline(&[("a", Some(1))]);
+        let report = report(&[("a", Some(2))]);
+        assert!(!baseline.compare(&report, 0.5).mrr_regressed);
+    }
+}
@@ -0,0 +1,61 @@
+//! `--bless` mode: auto-generate or refresh a test's expected answer from
+//! an actual completion run, written back into `completion.toml`.
+//!
+//! Inspired by compiletest's expected-output blessing workflow, this
+//! bootstraps truth data for newly added test projects and turns an
+//! intentional expectation update into a single command instead of
+//! hand-editing TOML.
+
+use anyhow::Context;
+use ty_ide::Completion;
+
+use ruff_db::system::SystemPath;
+
+use crate::{module_from_import, CompletionAnswer, TestSource};
+
+/// Bless a single test: run completion at each of its cursors and write the
+/// top-ranked completion back into its `completion.toml` as the expected
+/// answer.
+///
+/// Refuses to bless a case whose completion list is empty, since writing a
+/// bogus answer would silently create a broken test.
+pub fn bless(source: TestSource, tmp_eval_dir: &SystemPath) -> anyhow::Result<()> {
+    let name = source.name.clone();
+    let completion_toml_path = source.dir.join("completion.toml");
+    let mut truth = source.truth.clone();
+
+    let test = source.into_test(tmp_eval_dir)?;
+    for case in &test.cases {
+        let case_name = case.name.as_deref();
+        let completions = test.completions(case)?;
+        let answer = answer_from_top_completion(&completions).ok_or_else(|| {
+            anyhow::anyhow!(
+                "refusing to bless `{name}` case {case}: completion list is empty",
+                case = case_name.unwrap_or("<default>"),
+            )
+        })?;
+        truth.case_mut(case_name).answer = answer;
+    }
+
+    let data = toml::to_string_pretty(&truth)
+        .with_context(|| format!("failed to serialize blessed truth data for `{name}`"))?;
+    std::fs::write(completion_toml_path.as_std_path(), data).with_context(|| {
+        format!("failed to write blessed truth data to `{completion_toml_path}`")
+    })?;
+    println!("blessed `{name}`");
+    Ok(())
+}
+
+/// Derive the expected answer from the top-ranked completion at a cursor.
+///
+/// Returns `None` when the completion list was empty.
+fn answer_from_top_completion(completions: &[Completion<'_>]) -> Option<CompletionAnswer> {
+    let top = completions.first()?;
+    Some(CompletionAnswer {
+        symbol: top.name.to_string(),
+        module: top
+            .import
+            .as_ref()
+            .and_then(|edit| module_from_import(edit.content())),
+    })
+}
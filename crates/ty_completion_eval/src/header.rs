@@ -0,0 +1,243 @@
+//! Inline comment directives, read out of a test's Python source files.
+//!
+//! Following compiletest's header system, a test can carry its truth data
+//! (and settings) directly in its source as specially-formatted comments,
+//! instead of (or in addition to) a sibling `completion.toml`:
+//!
+//! ```python
+//! # ty-expect-symbol: foo
+//! # ty-expect-module: pkg.mod
+//! # ty-settings-auto-import: true
+//! ```
+//!
+//! Each directive is a `#`-prefixed line of the form `ty-<name>: <value>`,
+//! and may appear anywhere in the file. A directive may also target a named
+//! cursor case (see the crate-level docs on `<CURSOR:name>`) by bracketing
+//! the case name before the colon, e.g. `# ty-expect-symbol[attr]: foo`. A
+//! directive with no bracketed case name applies to the bare `<CURSOR>`
+//! case. When both a `completion.toml` and inline directives are present,
+//! the directives win.
+
+use std::collections::BTreeMap;
+
+use crate::CaseTruth;
+
+const EXPECT_SYMBOL: &str = "ty-expect-symbol";
+const EXPECT_MODULE: &str = "ty-expect-module";
+const SETTINGS_AUTO_IMPORT: &str = "ty-settings-auto-import";
+
+/// The truth data (and settings) parsed out of a test's inline directives,
+/// keyed by cursor case (`None` for the bare `<CURSOR>` case).
+#[derive(Clone, Debug, Default)]
+pub struct Directives {
+    cases: BTreeMap<Option<String>, CaseDirectives>,
+}
+
+/// The directives found for a single cursor case.
+///
+/// Each field is `None` when its directive wasn't present, so that applying
+/// a `CaseDirectives` onto a [`CaseTruth`] only overrides what was actually
+/// specified.
+#[derive(Clone, Debug, Default)]
+struct CaseDirectives {
+    symbol: Option<String>,
+    module: Option<String>,
+    auto_import: Option<bool>,
+}
+
+impl Directives {
+    /// Scan `data` for directive comments.
+    pub fn parse(data: &[u8]) -> Directives {
+        let mut directives = Directives::default();
+        for line in String::from_utf8_lossy(data).lines() {
+            let Some(comment) = line.trim_start().strip_prefix('#') else {
+                continue;
+            };
+            let Some((name, case, value)) = parse_directive(comment.trim_start()) else {
+                continue;
+            };
+            let case_directives = directives.cases.entry(case).or_default();
+            match name {
+                EXPECT_SYMBOL => case_directives.symbol = Some(value.to_string()),
+                EXPECT_MODULE => case_directives.module = Some(value.to_string()),
+                SETTINGS_AUTO_IMPORT => case_directives.auto_import = value.parse::<bool>().ok(),
+                _ => unreachable!("parse_directive only returns known directive names"),
+            }
+        }
+        directives
+    }
+
+    /// Merge `other` into `self`, with `other`'s directives taking
+    /// precedence wherever both specify something for the same case.
+    pub fn merge(&mut self, other: Directives) {
+        for (case, other_directives) in other.cases {
+            self.cases.entry(case).or_default().merge(other_directives);
+        }
+    }
+
+    /// Apply the directives found for `case` on top of `truth`, overriding
+    /// only the fields that were actually specified inline.
+    pub fn apply_to_case(&self, case: Option<&str>, truth: CaseTruth) -> CaseTruth {
+        match self.cases.get(&case.map(str::to_string)) {
+            Some(directives) => directives.apply_to(truth),
+            None => truth,
+        }
+    }
+}
+
+impl CaseDirectives {
+    fn merge(&mut self, other: CaseDirectives) {
+        if other.symbol.is_some() {
+            self.symbol = other.symbol;
+        }
+        if other.module.is_some() {
+            self.module = other.module;
+        }
+        if other.auto_import.is_some() {
+            self.auto_import = other.auto_import;
+        }
+    }
+
+    fn apply_to(&self, mut truth: CaseTruth) -> CaseTruth {
+        if let Some(symbol) = &self.symbol {
+            truth.answer.symbol = symbol.clone();
+        }
+        if let Some(module) = &self.module {
+            truth.answer.module = Some(module.clone());
+        }
+        if let Some(auto_import) = self.auto_import {
+            truth.settings.auto_import = auto_import;
+        }
+        truth
+    }
+}
+
+/// Parses a single directive comment (with the leading `#` already
+/// stripped), returning the directive's name, optional case name, and
+/// value.
+fn parse_directive(comment: &str) -> Option<(&'static str, Option<String>, &str)> {
+    for name in [EXPECT_SYMBOL, EXPECT_MODULE, SETTINGS_AUTO_IMPORT] {
+        let Some(rest) = comment.strip_prefix(name) else {
+            continue;
+        };
+        if let Some(rest) = rest.strip_prefix('[') {
+            let (case, rest) = rest.split_once(']')?;
+            let value = rest.trim_start().strip_prefix(':')?;
+            return Some((name, Some(case.trim().to_string()), value.trim()));
+        }
+        if let Some(value) = rest.strip_prefix(':') {
+            return Some((name, None, value.trim()));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_directive_untargeted() {
+        assert_eq!(
+            parse_directive("ty-expect-symbol: foo"),
+            Some((EXPECT_SYMBOL, None, "foo"))
+        );
+        assert_eq!(
+            parse_directive("ty-expect-module: pkg.mod"),
+            Some((EXPECT_MODULE, None, "pkg.mod"))
+        );
+        assert_eq!(
+            parse_directive("ty-settings-auto-import: true"),
+            Some((SETTINGS_AUTO_IMPORT, None, "true"))
+        );
+    }
+
+    #[test]
+    fn parse_directive_targeted() {
+        assert_eq!(
+            parse_directive("ty-expect-symbol[attr]: foo"),
+            Some((EXPECT_SYMBOL, Some("attr".to_string()), "foo"))
+        );
+        // Whitespace around the bracketed case name and the value is
+        // trimmed.
+        assert_eq!(
+            parse_directive("ty-expect-symbol[ attr ]:   foo  "),
+            Some((EXPECT_SYMBOL, Some("attr".to_string()), "foo"))
+        );
+    }
+
+    #[test]
+    fn parse_directive_unterminated_case_is_not_a_directive() {
+        assert_eq!(parse_directive("ty-expect-symbol[attr: foo"), None);
+    }
+
+    #[test]
+    fn parse_directive_unknown_name_is_not_a_directive() {
+        assert_eq!(parse_directive("ty-expect-unknown: foo"), None);
+        assert_eq!(parse_directive("not a directive at all"), None);
+    }
+
+    #[test]
+    fn apply_to_case_untargeted_overrides_default() {
+        let mut directives = Directives::default();
+        directives.cases.insert(
+            None,
+            CaseDirectives {
+                symbol: Some("foo".to_string()),
+                module: None,
+                auto_import: Some(true),
+            },
+        );
+
+        let mut truth = CaseTruth::default();
+        truth.answer.symbol = "unset".to_string();
+        truth.answer.module = Some("unset.mod".to_string());
+
+        let truth = directives.apply_to_case(None, truth);
+        assert_eq!(truth.answer.symbol, "foo");
+        // `module` wasn't specified, so the original value is untouched.
+        assert_eq!(truth.answer.module, Some("unset.mod".to_string()));
+        assert!(truth.settings.auto_import);
+    }
+
+    #[test]
+    fn apply_to_case_targeted_only_affects_its_case() {
+        let mut directives = Directives::default();
+        directives.cases.insert(
+            Some("attr".to_string()),
+            CaseDirectives {
+                symbol: Some("bar".to_string()),
+                module: None,
+                auto_import: None,
+            },
+        );
+
+        let mut matching = CaseTruth::default();
+        matching.answer.symbol = "unset".to_string();
+        assert_eq!(
+            directives
+                .apply_to_case(Some("attr"), matching)
+                .answer
+                .symbol,
+            "bar"
+        );
+
+        let mut other = CaseTruth::default();
+        other.answer.symbol = "unset".to_string();
+        assert_eq!(
+            directives.apply_to_case(Some("other"), other).answer.symbol,
+            "unset"
+        );
+    }
+
+    #[test]
+    fn apply_to_case_with_no_directives_is_a_no_op() {
+        let directives = Directives::default();
+        let mut truth = CaseTruth::default();
+        truth.answer.symbol = "unchanged".to_string();
+        assert_eq!(
+            directives.apply_to_case(None, truth.clone()).answer.symbol,
+            truth.answer.symbol
+        );
+    }
+}
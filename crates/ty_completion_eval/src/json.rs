@@ -0,0 +1,91 @@
+//! Machine-readable `--format json` report output.
+//!
+//! Mirrors compiletest's `json.rs` structured output: one record per test
+//! case plus a final aggregate, so dashboards can track completion quality
+//! over time and CI can diff two runs programmatically instead of scraping
+//! stdout.
+
+use serde::Serialize;
+
+use crate::{CaseResult, CorpusReport};
+
+/// A single test case's result.
+#[derive(Debug, Serialize)]
+struct TestRecord {
+    name: String,
+    cursor_path: String,
+    cursor_offset: usize,
+    expected_symbol: String,
+    expected_module: Option<String>,
+    rank: Option<usize>,
+    reciprocal_rank: f64,
+    top_1: bool,
+    top_5: bool,
+    top_10: bool,
+    uv_sync_seconds: f64,
+    completion_seconds: f64,
+}
+
+impl From<&CaseResult> for TestRecord {
+    fn from(result: &CaseResult) -> TestRecord {
+        TestRecord {
+            name: result.score.name.clone(),
+            cursor_path: result.cursor_path.clone(),
+            cursor_offset: result.cursor_offset,
+            expected_symbol: result.expected_symbol.clone(),
+            expected_module: result.expected_module.clone(),
+            rank: result.score.rank,
+            reciprocal_rank: result.score.reciprocal_rank(),
+            top_1: result.score.top_n(1),
+            top_5: result.score.top_n(5),
+            top_10: result.score.top_n(10),
+            uv_sync_seconds: result.uv_sync_duration.as_secs_f64(),
+            completion_seconds: result.completion_duration.as_secs_f64(),
+        }
+    }
+}
+
+/// The aggregate corpus statistics.
+#[derive(Debug, Serialize)]
+struct Aggregate {
+    mrr: f64,
+    recall_at_1: f64,
+    recall_at_5: f64,
+    recall_at_10: f64,
+    not_found: usize,
+}
+
+impl From<&CorpusReport> for Aggregate {
+    fn from(report: &CorpusReport) -> Aggregate {
+        Aggregate {
+            mrr: report.mrr(),
+            recall_at_1: report.recall_at(1),
+            recall_at_5: report.recall_at(5),
+            recall_at_10: report.recall_at(10),
+            not_found: report.not_found(),
+        }
+    }
+}
+
+/// A full `--format json` report: one record per test case plus a final
+/// aggregate over the whole corpus.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    tests: Vec<TestRecord>,
+    aggregate: Aggregate,
+}
+
+impl Report {
+    pub fn new(results: &[CaseResult], corpus: &CorpusReport) -> Report {
+        Report {
+            tests: results.iter().map(TestRecord::from).collect(),
+            aggregate: Aggregate::from(corpus),
+        }
+    }
+
+    /// Print this report as JSON to stdout.
+    pub fn print(&self) -> anyhow::Result<()> {
+        println!("{}", serde_json::to_string(self)?);
+        Ok(())
+    }
+}
@@ -1,9 +1,31 @@
 /*!
 # Caveats
 
-Source files have the substring `<CURSOR>` removed and its position recorded.
-If this substring occurs more than once (or less than once) throughout a project,
-then that particular test is considered invalid.
+Source files have the substring `<CURSOR>` (or `<CURSOR:case>`, see below)
+removed and its position recorded. A project with no such markers at all is
+considered invalid.
+
+# Multiple cursors
+
+A project may contain more than one cursor marker, as long as each is
+distinguished by a case name: `<CURSOR:attr>`, `<CURSOR:import>`, and so on.
+A bare `<CURSOR>` (no case name) may additionally appear at most once, since
+it would otherwise be ambiguous which occurrence it refers to. Each case is
+evaluated independently, as if it were its own test, which lets a single
+realistic project exercise many completion positions (attribute access,
+import, keyword argument, ...) without duplicating the whole project
+directory and re-running `uv sync` once per position.
+
+# Truth data
+
+Truth data for a test is ordinarily read from a sibling `completion.toml`,
+either at the top level (for the unnamed case) or under `[cases.<name>]` (for
+a named case). A test's Python source files may instead (or additionally)
+carry the same information as inline comment directives, e.g.
+`# ty-expect-symbol: foo` or, for a named case, `# ty-expect-symbol[attr]:
+foo`. See [`header`] for the full directive syntax. When both are present,
+inline directives take precedence, so a single file can be fully
+self-contained: cursors, expectations and settings all in one place.
 */
 
 use anyhow::{Context, anyhow};
@@ -14,7 +36,38 @@ use ruff_db::system::{OsSystem, SystemPath, SystemPathBuf};
 use ty_ide::Completion;
 use ty_project::{ProjectDatabase, ProjectMetadata};
 
+pub use crate::score::{CaseResult, CorpusReport, Score};
+
+mod baseline;
+mod bless;
+mod header;
+mod json;
+mod runner;
+mod score;
+
 pub fn run() -> anyhow::Result<()> {
+    // Whether we're (re-)generating the baseline instead of comparing
+    // against it. See `baseline` for the ratchet this supports.
+    let save_baseline = std::env::args().any(|arg| arg == "--save-baseline");
+    // An optional substring of `TestSource::name` to filter the corpus down
+    // to. The first non-flag argument, if any, skipping over the values of
+    // `--format` and `--mrr-threshold` so `--format json` isn't mistaken
+    // for a filter named "json".
+    let filter = parse_filter_arg();
+    // Whether to emit a single `--format json` report instead of the
+    // human-readable output below.
+    let args: Vec<String> = std::env::args().collect();
+    let format_json = args
+        .windows(2)
+        .any(|w| w[0] == "--format" && w[1] == "json");
+    // Whether to (re-)generate each test's expected answer from a fresh
+    // completion run instead of evaluating against the existing one. See
+    // `bless` for details.
+    let do_bless = std::env::args().any(|arg| arg == "--bless");
+    // The fractional MRR drop below which a change against the baseline is
+    // treated as noise rather than a regression. See `baseline`.
+    let mrr_threshold = parse_mrr_threshold_arg()?;
+
     // The base path to which all CLI arguments are relative to.
     let cwd = {
         let cwd = std::env::current_dir().context("Failed to get the current working directory")?;
@@ -43,30 +96,146 @@ pub fn run() -> anyhow::Result<()> {
     let tmp_eval_dir = SystemPath::new("/tmp/ty-completion-eval");
     std::fs::create_dir_all(tmp_eval_dir).with_context(|| tmp_eval_dir.to_string())?;
 
-    for source in TestSource::all(&truth)? {
-        let test = source.into_test(&tmp_eval_dir)?;
-        for c in test.completions()? {
-            if let Some(ref edit) = c.import {
-                println!("{} import {:?}", c.name, edit.content());
-            } else {
-                println!("{}", c.name);
+    if do_bless {
+        for source in TestSource::all(&truth)? {
+            let name = source.name.clone();
+            bless::bless(source, tmp_eval_dir)
+                .with_context(|| format!("failed to bless `{name}`"))?;
+        }
+        return Ok(());
+    }
+
+    let (outcomes, summary) =
+        runner::run_corpus(TestSource::all(&truth)?, tmp_eval_dir, filter.as_deref());
+
+    let mut report = CorpusReport::default();
+    let mut results = Vec::new();
+    for outcome in outcomes {
+        match outcome {
+            runner::Outcome::Passed {
+                results: case_results,
+                ..
+            } => {
+                for result in case_results {
+                    if !format_json {
+                        println!("{}", result.score.summary_line());
+                    }
+                    report.scores.push(result.score.clone());
+                    results.push(result);
+                }
+            }
+            runner::Outcome::Failed { name, error } => {
+                eprintln!("{name}: FAILED: {error:#}");
             }
         }
-        dbg!(&test.answer);
+    }
+
+    if format_json {
+        json::Report::new(&results, &report).print()?;
+        anyhow::ensure!(
+            summary.failed == 0,
+            "{} test(s) failed to run to completion",
+            summary.failed
+        );
+        return Ok(());
+    }
+
+    report.print_summary();
+    summary.print();
+    anyhow::ensure!(
+        summary.failed == 0,
+        "{} test(s) failed to run to completion",
+        summary.failed
+    );
+
+    let baseline_path = truth
+        .parent()
+        .expect("truth directory has a parent")
+        .join("baseline.json");
+    if save_baseline {
+        baseline::Baseline::from_report(&report).save(baseline_path.as_std_path())?;
+        println!("wrote baseline to `{baseline_path}`");
+    } else if baseline_path.as_std_path().exists() {
+        let baseline = baseline::Baseline::load(baseline_path.as_std_path())?;
+        let comparison = baseline.compare(&report, mrr_threshold);
+        comparison.print_summary();
+        anyhow::ensure!(
+            !comparison.has_regression(),
+            "completion quality regressed relative to baseline `{baseline_path}`; \
+             see above for details, or run with `--save-baseline` if this is expected",
+        );
+    } else {
+        println!(
+            "no baseline found at `{baseline_path}`; run with `--save-baseline` to create one"
+        );
     }
 
     Ok(())
 }
 
+/// A best-effort extraction of the imported module from an import edit's
+/// text, e.g. `"from pkg.mod import foo\n"` -> `Some("pkg.mod")`.
+fn module_from_import(content: &str) -> Option<String> {
+    let rest = content.trim_start().strip_prefix("from ")?;
+    let (module, _) = rest.split_once(" import ")?;
+    Some(module.trim().to_string())
+}
+
+/// Scans argv for the positional name filter: the first non-flag argument,
+/// skipping the value that follows `--format` or `--mrr-threshold` so
+/// `--format json` isn't mistaken for a filter named `"json"`.
+fn parse_filter_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--format" || arg == "--mrr-threshold" {
+            args.next();
+            continue;
+        }
+        if !arg.starts_with("--") {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+/// Scans argv for `--mrr-threshold <value>`, the fractional MRR drop below
+/// which `baseline::Baseline::compare` treats a change as noise rather than
+/// a regression. Falls back to [`baseline::DEFAULT_MRR_NOISE_THRESHOLD`]
+/// when the flag isn't present.
+fn parse_mrr_threshold_arg() -> anyhow::Result<f64> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg != "--mrr-threshold" {
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| anyhow!("`--mrr-threshold` requires a value"))?;
+        return value
+            .parse()
+            .with_context(|| format!("`--mrr-threshold` value `{value}` is not a valid number"));
+    }
+    Ok(baseline::DEFAULT_MRR_NOISE_THRESHOLD)
+}
+
 /// A test corresponding to a Python project.
 ///
-/// The test is oriented in such a way that we have a single
-/// "cursor" position. This allows us to ask for completions
-/// at that position.
+/// A test may exercise more than one completion position: each `<CURSOR>`
+/// (or `<CURSOR:name>`) found in the project becomes its own [`Case`].
 struct Test {
     db: ProjectDatabase,
     dir: SystemPathBuf,
     name: String,
+    cases: Vec<Case>,
+    /// How long `uv sync` took to set up this test's virtual environment.
+    uv_sync_duration: std::time::Duration,
+}
+
+/// A single cursor position within a [`Test`], along with the truth data to
+/// evaluate completions against at that position.
+struct Case {
+    /// The case name from `<CURSOR:name>`, or `None` for a bare `<CURSOR>`.
+    name: Option<String>,
     cursor: Cursor,
     answer: CompletionAnswer,
     settings: ty_ide::CompletionSettings,
@@ -75,8 +244,8 @@ struct Test {
 impl Test {
     fn new(
         project_path: &SystemPath,
-        truth: CompletionTruth,
-        cursor: Cursor,
+        cases: Vec<(Cursor, CaseTruth)>,
+        uv_sync_duration: std::time::Duration,
     ) -> anyhow::Result<Test> {
         let name = project_path.file_name().ok_or_else(|| {
             anyhow::anyhow!("project directory `{project_path}` does not contain a base name")
@@ -86,28 +255,90 @@ impl Test {
         let mut project_metadata = ProjectMetadata::discover(&project_path, &system)?;
         project_metadata.apply_configuration_files(&system)?;
         let db = ProjectDatabase::new(project_metadata, system)?;
+        let cases = cases
+            .into_iter()
+            .map(|(cursor, truth)| Case {
+                name: cursor.case.clone(),
+                cursor,
+                answer: truth.answer,
+                settings: truth.settings.into(),
+            })
+            .collect();
         Ok(Test {
             db,
             dir: project_path.to_path_buf(),
             name: name.to_string(),
-            cursor,
-            answer: truth.answer,
-            settings: truth.settings.into(),
+            cases,
+            uv_sync_duration,
         })
     }
 
-    fn completions(&self) -> anyhow::Result<Vec<Completion<'_>>> {
-        let file = system_path_to_file(&self.db, &self.cursor.path)
-            .with_context(|| format!("failed to get database file for `{}`", self.cursor.path))?;
-        let offset = ruff_text_size::TextSize::try_from(self.cursor.offset).with_context(|| {
+    fn completions(&self, case: &Case) -> anyhow::Result<Vec<Completion<'_>>> {
+        let file = system_path_to_file(&self.db, &case.cursor.path)
+            .with_context(|| format!("failed to get database file for `{}`", case.cursor.path))?;
+        let offset = ruff_text_size::TextSize::try_from(case.cursor.offset).with_context(|| {
             format!(
                 "failed to convert `<CURSOR>` file offset `{}` to 32-bit integer",
-                self.cursor.offset
+                case.cursor.offset
             )
         })?;
-        let completions = ty_ide::completion(&self.db, &self.settings, file, offset);
+        let completions = ty_ide::completion(&self.db, &case.settings, file, offset);
         Ok(completions)
     }
+
+    /// Run completion at each of this test's cursors and score the results
+    /// against each case's expected answer.
+    fn evaluate(&self) -> anyhow::Result<Vec<CaseResult>> {
+        self.cases
+            .iter()
+            .map(|case| {
+                let start = std::time::Instant::now();
+                let completions = self.completions(case)?;
+                let completion_duration = start.elapsed();
+                let rank = completions
+                    .iter()
+                    .position(|c| Self::matches(case, c))
+                    .map(|index| index + 1);
+                Ok(CaseResult {
+                    score: Score::new(self.score_name(case), rank),
+                    cursor_path: case.cursor.path.to_string(),
+                    cursor_offset: case.cursor.offset,
+                    expected_symbol: case.answer.symbol.clone(),
+                    expected_module: case.answer.module.clone(),
+                    uv_sync_duration: self.uv_sync_duration,
+                    completion_duration,
+                })
+            })
+            .collect()
+    }
+
+    /// The name under which a case's score is reported.
+    fn score_name(&self, case: &Case) -> String {
+        match &case.name {
+            Some(case_name) => format!("{}::{case_name}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Returns true when `completion` satisfies `case`'s expected answer.
+    ///
+    /// The symbol name must always match. When the answer also specifies a
+    /// module, the completion must carry an import edit that resolves to
+    /// exactly that module. A completion with no import edit (i.e. one
+    /// already in scope) can only satisfy a module-less answer.
+    fn matches(case: &Case, completion: &Completion<'_>) -> bool {
+        if completion.name != case.answer.symbol {
+            return false;
+        }
+        let completion_module = completion
+            .import
+            .as_ref()
+            .and_then(|edit| module_from_import(edit.content()));
+        match &case.answer.module {
+            None => completion_module.is_none(),
+            Some(module) => completion_module.as_deref() == Some(module.as_str()),
+        }
+    }
 }
 
 impl std::fmt::Debug for Test {
@@ -116,24 +347,61 @@ impl std::fmt::Debug for Test {
             .field("db", &"<ProjectDatabase>")
             .field("dir", &self.dir)
             .field("name", &self.name)
-            .field("cursor", &self.cursor)
-            .field("answer", &self.answer)
-            .field("settings", &self.settings)
+            .field("cases", &self.cases.len())
             .finish()
     }
 }
 
-/// Truth data for a single completion evaluation test.
-#[derive(Debug, Default, serde::Deserialize)]
+/// Truth data for a completion evaluation test, covering the unnamed
+/// (default) cursor case plus any additional named cases declared via
+/// `[cases.<name>]` (mirroring `<CURSOR:name>` in the test's sources).
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct CompletionTruth {
+    #[serde(flatten)]
+    default: CaseTruth,
+    #[serde(default)]
+    cases: std::collections::BTreeMap<String, CaseTruth>,
+}
+
+impl CompletionTruth {
+    /// The truth data for a single case, falling back to the default case's
+    /// truth data when `name` has no dedicated `[cases.<name>]` entry.
+    fn case(&self, name: Option<&str>) -> CaseTruth {
+        match name {
+            None => self.default.clone(),
+            Some(name) => self
+                .cases
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| self.default.clone()),
+        }
+    }
+
+    /// A mutable handle to the truth data for a single case, creating a new
+    /// `[cases.<name>]` entry if one doesn't already exist. Used by
+    /// `--bless` to write back a freshly-computed answer.
+    fn case_mut(&mut self, name: Option<&str>) -> &mut CaseTruth {
+        match name {
+            None => &mut self.default,
+            Some(name) => self.cases.entry(name.to_string()).or_default(),
+        }
+    }
+}
+
+/// The truth data for a single cursor case: its expected answer and the
+/// settings to evaluate completion with.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CaseTruth {
+    #[serde(default)]
     answer: CompletionAnswer,
     #[serde(default)]
     settings: CompletionSettings,
 }
 
-/// The answer for this completion test.
-#[derive(Debug, Default, serde::Deserialize)]
+/// The answer for a single completion case.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct CompletionAnswer {
     symbol: String,
@@ -141,7 +409,7 @@ struct CompletionAnswer {
 }
 
 /// Settings to forward to our completion routine.
-#[derive(Debug, Default, serde::Deserialize)]
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct CompletionSettings {
     #[serde(default)]
@@ -164,7 +432,8 @@ struct TestSource {
     /// The name of this test (the basename of `dir`).
     name: String,
     /// The "truth" data for this test along with any
-    /// settings. This is pulled from `{dir}/completion.toml`.
+    /// settings. This is pulled from `{dir}/completion.toml`, and is later
+    /// overridden by any inline directives found in the test's sources.
     truth: CompletionTruth,
 }
 
@@ -199,12 +468,19 @@ impl TestSource {
             anyhow::anyhow!("truth source directory `{dir}` does not contain a base name")
         })?;
 
+        // `completion.toml` is optional: a test may instead (or additionally)
+        // specify its truth data as inline directives, parsed later in
+        // `into_test` once we know which file(s) actually carry them.
         let truth_path = dir.join("completion.toml");
-        let truth_data = std::fs::read(truth_path.as_std_path())
-            .with_context(|| format!("failed to read truth data at `{truth_path}`"))?;
-        let truth = toml::from_slice(&truth_data).with_context(|| {
-            format!("failed to parse TOML completion truth data from `{truth_path}`")
-        })?;
+        let truth = if truth_path.as_std_path().exists() {
+            let truth_data = std::fs::read(truth_path.as_std_path())
+                .with_context(|| format!("failed to read truth data at `{truth_path}`"))?;
+            toml::from_slice(&truth_data).with_context(|| {
+                format!("failed to parse TOML completion truth data from `{truth_path}`")
+            })?
+        } else {
+            CompletionTruth::default()
+        };
 
         Ok(TestSource {
             dir: dir.to_path_buf(),
@@ -219,12 +495,22 @@ impl TestSource {
     /// This includes running `uv sync` to set up a full virtual environment.
     fn into_test(self, parent_dst_dir: &SystemPath) -> anyhow::Result<Test> {
         let dir = parent_dst_dir.join(&self.name);
-        let cursor = copy_project(&self.dir, &dir)?;
+        let (cursors, directives) = copy_project(&self.dir, &dir)?;
+        let cases = cursors
+            .into_iter()
+            .map(|cursor| {
+                let truth = self.truth.case(cursor.case.as_deref());
+                let truth = directives.apply_to_case(cursor.case.as_deref(), truth);
+                (cursor, truth)
+            })
+            .collect();
+        let uv_sync_start = std::time::Instant::now();
         let uv_sync_output = std::process::Command::new("uv")
             .arg("sync")
             .current_dir(dir.as_std_path())
             .output()
             .with_context(|| format!("failed to run `uv sync` in `{dir}`"))?;
+        let uv_sync_duration = uv_sync_start.elapsed();
         if !uv_sync_output.status.success() {
             let code = uv_sync_output
                 .status
@@ -234,26 +520,34 @@ impl TestSource {
             let stderr = bstr::BStr::new(&uv_sync_output.stderr);
             anyhow::bail!("`uv sync` failed to run with exit code `{code}`, stderr: {stderr}")
         }
-        Test::new(&dir, self.truth, cursor)
+        Test::new(&dir, cases, uv_sync_duration)
     }
 }
 
-/// The location of `<CURSOR>` within a single Python project.
+/// The location of a `<CURSOR>` (or `<CURSOR:case>`) marker within a single
+/// Python project.
 #[derive(Debug)]
 struct Cursor {
     path: SystemPathBuf,
     offset: usize,
+    /// The case name from `<CURSOR:name>`, or `None` for a bare `<CURSOR>`.
+    case: Option<String>,
 }
 
 /// Copy the Python project from `src_dir` to `dst_dir`.
 ///
-/// This also looks for a singular occurrence of `<CURSOR>`
-/// among the project files and returns its position. The
-/// original `<CURSOR>` string is deleted.
-fn copy_project(src_dir: &SystemPath, dst_dir: &SystemPath) -> anyhow::Result<Cursor> {
+/// This also looks for every `<CURSOR>`/`<CURSOR:case>` marker among the
+/// project files and returns their positions, along with any inline truth
+/// directives (see [`header`]) found while copying. The original markers
+/// are deleted from the copied files.
+fn copy_project(
+    src_dir: &SystemPath,
+    dst_dir: &SystemPath,
+) -> anyhow::Result<(Vec<Cursor>, header::Directives)> {
     std::fs::create_dir_all(dst_dir).with_context(|| dst_dir.to_string())?;
 
-    let mut cursor: Option<Cursor> = None;
+    let mut cursors: Vec<Cursor> = vec![];
+    let mut directives = header::Directives::default();
     let read_dir = src_dir
         .as_std_path()
         .read_dir()
@@ -267,57 +561,204 @@ fn copy_project(src_dir: &SystemPath, dst_dir: &SystemPath) -> anyhow::Result<Cu
             .file_name()
             .ok_or_else(|| anyhow::anyhow!("path `{src}` is missing a basename"))?;
         let dst = dst_dir.join(name);
-        if let Some(new_cursor) = copy_file(&src, &dst)? {
-            if let Some(cursor) = cursor {
+        let (new_cursors, file_directives) = copy_file(&src, &dst)?;
+        for new_cursor in new_cursors {
+            if let Some(existing) = cursors.iter().find(|c| c.case == new_cursor.case) {
                 anyhow::bail!(
-                    "found `<CURSOR>` in both `{path1}` and `{path2}`, \
-                     but it must occur in exactly one file",
-                    path1 = cursor.path,
+                    "found more than one `<CURSOR>` marker for case {case} (in `{path1}` \
+                     and `{path2}`), but each case must have exactly one cursor",
+                    case = new_cursor
+                        .case
+                        .as_deref()
+                        .map_or("<default>".to_string(), |case| format!("`{case}`")),
+                    path1 = existing.path,
                     path2 = new_cursor.path,
                 );
             }
-            cursor = Some(new_cursor);
+            cursors.push(new_cursor);
         }
+        directives.merge(file_directives);
     }
-    cursor.ok_or_else(|| {
-        anyhow::anyhow!(
-            "could not find any `<CURSOR>` substring in any of the files in `{src_dir}`",
-        )
-    })
+    anyhow::ensure!(
+        !cursors.is_empty(),
+        "could not find any `<CURSOR>` substring in any of the files in `{src_dir}`",
+    );
+    // `read_dir`'s iteration order is platform-dependent, so without this
+    // the cases of a multi-cursor test (and thus the results derived from
+    // them) would come out in a different order on every run.
+    cursors.sort_by(|a, b| a.case.cmp(&b.case).then(a.offset.cmp(&b.offset)));
+    Ok((cursors, directives))
 }
 
-/// Copies `src` to `dst` while looking for `<CURSOR>`.
+/// Copies `src` to `dst` while looking for `<CURSOR>`/`<CURSOR:case>`
+/// markers and any inline truth directives.
 ///
-/// If a `<CURSOR>` is found, then it is replaced with the empty string
-/// and its position is returned.
+/// Every marker found is replaced with the empty string and its position is
+/// returned.
 ///
 /// # Errors
 ///
-/// When an underlying I/O error occurs or when `<CURSOR>` occurs more than
-/// once.
-fn copy_file(src: &SystemPath, dst: &SystemPath) -> anyhow::Result<Option<Cursor>> {
-    static CURSOR: &[u8] = b"<CURSOR>";
+/// When an underlying I/O error occurs, when a `<CURSOR:...>` marker is
+/// unterminated, or when its case name is not valid UTF-8.
+fn copy_file(
+    src: &SystemPath,
+    dst: &SystemPath,
+) -> anyhow::Result<(Vec<Cursor>, header::Directives)> {
+    static CURSOR_PREFIX: &[u8] = b"<CURSOR";
 
     let src_data =
         std::fs::read(src).with_context(|| format!("failed to read `{src}` for copying"))?;
-    let mut cursor = None;
+    let directives = header::Directives::parse(&src_data);
+    let mut cursors = vec![];
     let mut new = Vec::with_capacity(src_data.len());
     let mut written_to = 0;
-    for (i, offset) in memmem::find_iter(&src_data, CURSOR).enumerate() {
-        anyhow::ensure!(
-            i == 0,
-            "found `<CURSOR>` more than once in `{src}` (must occur at most once)",
-        );
+    for offset in memmem::find_iter(&src_data, CURSOR_PREFIX) {
+        if offset < written_to {
+            // Inside a marker we've already consumed (e.g. `<CURSOR:<CURSOR>`).
+            continue;
+        }
+        let rest = &src_data[offset + CURSOR_PREFIX.len()..];
+        let (case, marker_len) = match rest.first() {
+            Some(b'>') => (None, CURSOR_PREFIX.len() + 1),
+            Some(b':') => {
+                let close = memchr::memchr(b'>', rest).ok_or_else(|| {
+                    anyhow::anyhow!("unterminated `<CURSOR:...>` marker in `{src}`")
+                })?;
+                let name = std::str::from_utf8(&rest[1..close]).map_err(|_| {
+                    anyhow::anyhow!("`<CURSOR:...>` case name in `{src}` is not valid UTF-8")
+                })?;
+                (Some(name.to_string()), CURSOR_PREFIX.len() + close + 1)
+            }
+            // Not actually a `<CURSOR>`/`<CURSOR:...>` marker; leave it alone.
+            _ => continue,
+        };
 
         new.extend_from_slice(&src_data[written_to..offset]);
-        written_to = offset + CURSOR.len();
-        cursor = Some(Cursor {
+        cursors.push(Cursor {
             path: dst.to_path_buf(),
-            offset,
+            offset: new.len(),
+            case,
         });
+        written_to = offset + marker_len;
     }
     new.extend_from_slice(&src_data[written_to..]);
     std::fs::write(dst, &new)
         .with_context(|| format!("failed to write contents of `{src}` to `{dst}`"))?;
-    Ok(cursor)
+    Ok((cursors, directives))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory unique to this test, cleaned up by the caller.
+    fn scratch_dir(name: &str) -> SystemPathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ty-completion-eval-test-{name}-{pid}-{tid:?}",
+            pid = std::process::id(),
+            tid = std::thread::current().id(),
+        ));
+        std::fs::create_dir_all(&dir).expect("failed to create scratch directory");
+        SystemPathBuf::from_path_buf(dir).expect("scratch directory path is not valid UTF-8")
+    }
+
+    /// Copies `contents` through [`copy_file`] in a scratch directory,
+    /// cleaning up afterwards regardless of the result.
+    fn copy_str(name: &str, contents: &str) -> anyhow::Result<Vec<Cursor>> {
+        let dir = scratch_dir(name);
+        let src = dir.join("src.py");
+        let dst = dir.join("dst.py");
+        std::fs::write(src.as_std_path(), contents).expect("failed to write source file");
+        let result = copy_file(&src, &dst).map(|(cursors, _)| cursors);
+        std::fs::remove_dir_all(dir.as_std_path()).ok();
+        result
+    }
+
+    #[test]
+    fn copy_file_bare_cursor() {
+        let cursors = copy_str("bare", "foo = 1\nbar.<CURSOR>\n").unwrap();
+        assert_eq!(cursors.len(), 1);
+        assert_eq!(cursors[0].case, None);
+        assert_eq!(cursors[0].offset, "foo = 1\nbar.".len());
+    }
+
+    #[test]
+    fn copy_file_named_cursor() {
+        let cursors = copy_str("named", "bar.<CURSOR:attr>\n").unwrap();
+        assert_eq!(cursors.len(), 1);
+        assert_eq!(cursors[0].case.as_deref(), Some("attr"));
+        assert_eq!(cursors[0].offset, "bar.".len());
+    }
+
+    #[test]
+    fn copy_file_multiple_named_cursors_recompute_offsets() {
+        let cursors = copy_str("multi", "bar.<CURSOR:attr>\nfoo(<CURSOR:kwarg>)\n").unwrap();
+        assert_eq!(cursors.len(), 2);
+        assert_eq!(cursors[0].case.as_deref(), Some("attr"));
+        assert_eq!(cursors[0].offset, "bar.".len());
+        assert_eq!(cursors[1].case.as_deref(), Some("kwarg"));
+        // Measured in the post-removal buffer, so it must not count either
+        // marker's own length.
+        assert_eq!(cursors[1].offset, "bar.\nfoo(".len());
+    }
+
+    #[test]
+    fn copy_file_non_marker_is_left_alone() {
+        let cursors = copy_str("non-marker", "x = '<CURSORx>'\n").unwrap();
+        assert!(cursors.is_empty());
+    }
+
+    #[test]
+    fn copy_file_strips_markers_from_output() {
+        let dir = scratch_dir("stripped");
+        let src = dir.join("src.py");
+        let dst = dir.join("dst.py");
+        std::fs::write(src.as_std_path(), "bar.<CURSOR>\n").expect("failed to write source file");
+        copy_file(&src, &dst).unwrap();
+        let written =
+            std::fs::read_to_string(dst.as_std_path()).expect("failed to read copied file");
+        std::fs::remove_dir_all(dir.as_std_path()).ok();
+        assert_eq!(written, "bar.\n");
+    }
+
+    #[test]
+    fn copy_file_unterminated_named_cursor_is_an_error() {
+        assert!(copy_str("unterminated", "bar.<CURSOR:attr\n").is_err());
+    }
+
+    #[test]
+    fn copy_project_rejects_duplicate_bare_cursor_across_files() {
+        let src_dir = scratch_dir("duplicate-src");
+        let dst_dir = scratch_dir("duplicate-dst");
+        std::fs::write(src_dir.join("a.py").as_std_path(), "<CURSOR>\n")
+            .expect("failed to write a.py");
+        std::fs::write(src_dir.join("b.py").as_std_path(), "<CURSOR>\n")
+            .expect("failed to write b.py");
+
+        let result = copy_project(&src_dir, &dst_dir);
+
+        std::fs::remove_dir_all(src_dir.as_std_path()).ok();
+        std::fs::remove_dir_all(dst_dir.as_std_path()).ok();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn copy_project_sorts_cursors_by_case_then_offset() {
+        let src_dir = scratch_dir("order-src");
+        let dst_dir = scratch_dir("order-dst");
+        // Deliberately out of case-name order, and spread across files, so
+        // this can't pass by coincidentally matching `read_dir`'s order.
+        std::fs::write(src_dir.join("z.py").as_std_path(), "<CURSOR:kwarg>\n")
+            .expect("failed to write z.py");
+        std::fs::write(src_dir.join("a.py").as_std_path(), "<CURSOR:attr>\n")
+            .expect("failed to write a.py");
+
+        let (cursors, _) = copy_project(&src_dir, &dst_dir).unwrap();
+
+        std::fs::remove_dir_all(src_dir.as_std_path()).ok();
+        std::fs::remove_dir_all(dst_dir.as_std_path()).ok();
+
+        let names: Vec<_> = cursors.iter().map(|c| c.case.as_deref()).collect();
+        assert_eq!(names, [Some("attr"), Some("kwarg")]);
+    }
 }
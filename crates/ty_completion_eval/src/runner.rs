@@ -0,0 +1,110 @@
+//! Concurrent execution of the test corpus, with name filtering and a
+//! pass/fail summary.
+//!
+//! Modeled on compiletest's libtest integration: since `uv sync` per project
+//! dominates a test's runtime, running the corpus across a small thread
+//! pool is a large practical speedup, and filtering by name lets a
+//! developer iterate on a single failing test quickly.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ruff_db::system::SystemPath;
+
+use crate::{CaseResult, TestSource};
+
+/// The result of running a single test through to completion.
+pub enum Outcome {
+    /// The test ran to completion; `results` has one entry per cursor case.
+    Passed {
+        name: String,
+        results: Vec<CaseResult>,
+    },
+    /// The test failed before it could be scored, e.g. `uv sync` or project
+    /// discovery failed.
+    Failed { name: String, error: anyhow::Error },
+}
+
+impl Outcome {
+    /// The name of the test this outcome belongs to.
+    fn name(&self) -> &str {
+        match self {
+            Outcome::Passed { name, .. } | Outcome::Failed { name, .. } => name,
+        }
+    }
+}
+
+/// Aggregate counts for a corpus run.
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub filtered_out: usize,
+    pub elapsed: Duration,
+}
+
+impl Summary {
+    pub fn print(&self) {
+        println!(
+            "{} passed; {} failed; {} filtered out, in {:.2?}",
+            self.passed, self.failed, self.filtered_out, self.elapsed,
+        );
+    }
+}
+
+/// Run every test in `sources` whose name contains `filter` (every test,
+/// when `filter` is `None`), spreading work across a thread pool.
+///
+/// Each test operates entirely within its own `{tmp_eval_dir}/{name}`
+/// directory, so independent tests never collide with one another.
+pub fn run_corpus(
+    sources: Vec<TestSource>,
+    tmp_eval_dir: &SystemPath,
+    filter: Option<&str>,
+) -> (Vec<Outcome>, Summary) {
+    let (matched, filtered_out): (Vec<_>, Vec<_>) = sources
+        .into_iter()
+        .partition(|source| filter.is_none_or(|f| source.name.contains(f)));
+
+    let start = Instant::now();
+    let queue = Mutex::new(VecDeque::from(matched));
+    let outcomes = Mutex::new(Vec::new());
+    let worker_count = std::thread::available_parallelism()
+        .map(std::num::NonZeroUsize::get)
+        .unwrap_or(1);
+
+    std::thread::scope(|scope| {
+        for _ in 0..worker_count {
+            scope.spawn(|| {
+                while let Some(source) = queue.lock().unwrap().pop_front() {
+                    let name = source.name.clone();
+                    let outcome = match source
+                        .into_test(tmp_eval_dir)
+                        .and_then(|test| test.evaluate())
+                    {
+                        Ok(results) => Outcome::Passed { name, results },
+                        Err(error) => Outcome::Failed { name, error },
+                    };
+                    outcomes.lock().unwrap().push(outcome);
+                }
+            });
+        }
+    });
+
+    // Worker threads push outcomes in whatever order they finish in, which
+    // varies run to run; sort by name so the report (and a `--format json`
+    // diff between two runs) is deterministic.
+    let mut outcomes = outcomes.into_inner().unwrap();
+    outcomes.sort_by(|a, b| a.name().cmp(b.name()));
+    let passed = outcomes
+        .iter()
+        .filter(|outcome| matches!(outcome, Outcome::Passed { .. }))
+        .count();
+    let summary = Summary {
+        passed,
+        failed: outcomes.len() - passed,
+        filtered_out: filtered_out.len(),
+        elapsed: start.elapsed(),
+    };
+    (outcomes, summary)
+}
@@ -0,0 +1,165 @@
+/// The result of evaluating a single test's completions against its
+/// expected answer.
+#[derive(Clone, Debug)]
+pub struct Score {
+    /// The name of the test (from `TestSource::name`) this score belongs to,
+    /// followed by `::<case>` for a named `<CURSOR:case>` case.
+    pub name: String,
+    /// The 1-based rank of the expected answer in the returned completion
+    /// list, or `None` if it did not appear at all.
+    pub rank: Option<usize>,
+}
+
+impl Score {
+    pub(crate) fn new(name: String, rank: Option<usize>) -> Score {
+        Score { name, rank }
+    }
+
+    /// The reciprocal rank of this test, i.e. `1 / rank`, or `0.0` when the
+    /// expected answer was not found.
+    pub fn reciprocal_rank(&self) -> f64 {
+        self.rank.map_or(0.0, |rank| 1.0 / rank as f64)
+    }
+
+    /// Whether the expected answer appeared within the top `n` completions.
+    pub fn top_n(&self, n: usize) -> bool {
+        self.rank.is_some_and(|rank| rank <= n)
+    }
+
+    /// A single human-readable line summarizing this test's result.
+    pub fn summary_line(&self) -> String {
+        match self.rank {
+            Some(rank) => format!(
+                "{name}: rank={rank} rr={rr:.4}",
+                name = self.name,
+                rr = self.reciprocal_rank()
+            ),
+            None => format!("{name}: not found", name = self.name),
+        }
+    }
+}
+
+/// The full result of evaluating a single cursor case: its [`Score`] plus
+/// the cursor/expectation/timing details needed for a `--format json`
+/// report (see the `json` module).
+#[derive(Clone, Debug)]
+pub struct CaseResult {
+    pub score: Score,
+    pub cursor_path: String,
+    pub cursor_offset: usize,
+    pub expected_symbol: String,
+    pub expected_module: Option<String>,
+    /// How long `uv sync` took for this case's parent test (shared across
+    /// every case in the same test).
+    pub uv_sync_duration: std::time::Duration,
+    /// How long the completion request for this specific case took.
+    pub completion_duration: std::time::Duration,
+}
+
+/// The aggregated scores for an entire corpus of completion tests.
+#[derive(Clone, Debug, Default)]
+pub struct CorpusReport {
+    pub scores: Vec<Score>,
+}
+
+impl CorpusReport {
+    /// The Mean Reciprocal Rank across every scored test.
+    pub fn mrr(&self) -> f64 {
+        if self.scores.is_empty() {
+            return 0.0;
+        }
+        self.scores.iter().map(Score::reciprocal_rank).sum::<f64>() / self.scores.len() as f64
+    }
+
+    /// The fraction of tests whose expected answer appeared in the top `n`
+    /// completions.
+    pub fn recall_at(&self, n: usize) -> f64 {
+        if self.scores.is_empty() {
+            return 0.0;
+        }
+        let hits = self.scores.iter().filter(|score| score.top_n(n)).count();
+        hits as f64 / self.scores.len() as f64
+    }
+
+    /// The number of tests whose expected answer never appeared in the
+    /// completion list.
+    pub fn not_found(&self) -> usize {
+        self.scores
+            .iter()
+            .filter(|score| score.rank.is_none())
+            .count()
+    }
+
+    /// Print a summary table of the corpus to stdout.
+    pub fn print_summary(&self) {
+        println!();
+        println!("corpus summary ({} tests)", self.scores.len());
+        println!("  MRR:       {:.4}", self.mrr());
+        println!("  recall@1:  {:.4}", self.recall_at(1));
+        println!("  recall@5:  {:.4}", self.recall_at(5));
+        println!("  recall@10: {:.4}", self.recall_at(10));
+        println!("  not found: {}", self.not_found());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reciprocal_rank_of_found_and_not_found() {
+        assert_eq!(Score::new("a".to_string(), Some(1)).reciprocal_rank(), 1.0);
+        assert_eq!(Score::new("a".to_string(), Some(4)).reciprocal_rank(), 0.25);
+        assert_eq!(Score::new("a".to_string(), None).reciprocal_rank(), 0.0);
+    }
+
+    #[test]
+    fn top_n_boundaries() {
+        let score = Score::new("a".to_string(), Some(5));
+        assert!(!score.top_n(1));
+        assert!(!score.top_n(4));
+        assert!(score.top_n(5));
+        assert!(score.top_n(10));
+        assert!(!Score::new("a".to_string(), None).top_n(10));
+    }
+
+    fn report(ranks: &[Option<usize>]) -> CorpusReport {
+        CorpusReport {
+            scores: ranks
+                .iter()
+                .enumerate()
+                .map(|(i, &rank)| Score::new(format!("test{i}"), rank))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn mrr_of_empty_corpus_is_zero() {
+        assert_eq!(report(&[]).mrr(), 0.0);
+    }
+
+    #[test]
+    fn mrr_averages_reciprocal_ranks() {
+        // 1/1, 1/2, 0 (not found) -> (1 + 0.5 + 0) / 3
+        assert_eq!(report(&[Some(1), Some(2), None]).mrr(), 0.5);
+    }
+
+    #[test]
+    fn recall_at_of_empty_corpus_is_zero() {
+        assert_eq!(report(&[]).recall_at(1), 0.0);
+    }
+
+    #[test]
+    fn recall_at_counts_hits_within_n() {
+        let report = report(&[Some(1), Some(5), None]);
+        assert_eq!(report.recall_at(1), 1.0 / 3.0);
+        assert_eq!(report.recall_at(5), 2.0 / 3.0);
+        assert_eq!(report.recall_at(10), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn not_found_counts_missing_ranks() {
+        assert_eq!(report(&[Some(1), None, None]).not_found(), 2);
+        assert_eq!(report(&[Some(1), Some(2)]).not_found(), 0);
+    }
+}